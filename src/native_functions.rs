@@ -2,8 +2,9 @@ use std::collections::HashMap;
 use std::ops::{Add, Deref};
 use crate::class::Class;
 use crate::function::{Function, NativeFunction};
+use crate::instance::Instance;
 use crate::value::Value;
-use crate::vm::{VM};
+use crate::vm::{List, VM};
 use rand::Rng;
 
 pub fn make_readln() -> NativeFunction {
@@ -20,68 +21,525 @@ pub fn make_random() -> NativeFunction {
     return NativeFunction { function: random };
 }
 
+pub fn make_seed() -> NativeFunction { return NativeFunction { function: seed }; }
+
+pub fn make_random_int() -> NativeFunction { return NativeFunction { function: random_int }; }
+
+pub fn make_random_range() -> NativeFunction { return NativeFunction { function: random_range }; }
+
+pub fn make_shuffle() -> NativeFunction { return NativeFunction { function: shuffle }; }
+
+pub fn make_choice() -> NativeFunction { return NativeFunction { function: choice }; }
+
 pub fn make_floor() -> NativeFunction { return NativeFunction { function: floor }; }
 
+pub fn make_ceil() -> NativeFunction { return NativeFunction { function: ceil }; }
+
+pub fn make_round() -> NativeFunction { return NativeFunction { function: round }; }
+
+pub fn make_trunc() -> NativeFunction { return NativeFunction { function: trunc }; }
+
+pub fn make_abs() -> NativeFunction { return NativeFunction { function: abs }; }
+
+pub fn make_sqrt() -> NativeFunction { return NativeFunction { function: sqrt }; }
+
+pub fn make_pow() -> NativeFunction { return NativeFunction { function: pow }; }
+
+pub fn make_min() -> NativeFunction { return NativeFunction { function: min }; }
+
+pub fn make_max() -> NativeFunction { return NativeFunction { function: max }; }
+
+pub fn make_log() -> NativeFunction { return NativeFunction { function: log }; }
+
+pub fn make_exp() -> NativeFunction { return NativeFunction { function: exp }; }
+
+pub fn make_sin() -> NativeFunction { return NativeFunction { function: sin }; }
+
+pub fn make_cos() -> NativeFunction { return NativeFunction { function: cos }; }
+
+pub fn make_tan() -> NativeFunction { return NativeFunction { function: tan }; }
+
+pub fn make_asin() -> NativeFunction { return NativeFunction { function: asin }; }
+
+pub fn make_acos() -> NativeFunction { return NativeFunction { function: acos }; }
+
+pub fn make_atan() -> NativeFunction { return NativeFunction { function: atan }; }
+
+/// Global constant `PI`.
+pub fn make_pi() -> Value { Value::Number(std::f64::consts::PI) }
+
+/// Global constant `E`.
+pub fn make_e() -> Value { Value::Number(std::f64::consts::E) }
+
 pub fn make_panic() -> NativeFunction { return NativeFunction { function: panic }; }
 
+pub fn make_error() -> NativeFunction { return NativeFunction { function: error }; }
+
+pub fn make_is_error() -> NativeFunction { return NativeFunction { function: is_error }; }
+
+pub fn make_try() -> NativeFunction { return NativeFunction { function: try_fn }; }
+
+pub fn make_read_file() -> NativeFunction { return NativeFunction { function: read_file }; }
+
+pub fn make_write_file() -> NativeFunction { return NativeFunction { function: write_file }; }
+
+pub fn make_args() -> NativeFunction { return NativeFunction { function: args_fn }; }
+
+pub fn make_eval() -> NativeFunction { return NativeFunction { function: eval }; }
+
+pub fn make_apply() -> NativeFunction { return NativeFunction { function: apply }; }
+pub fn make_create() -> NativeFunction { return NativeFunction { function: create }; }
+pub fn make_resume() -> NativeFunction { return NativeFunction { function: resume }; }
+
 fn readln(_: Vec<Value>, vm: &mut VM) -> Value {
     let mut s = String::new();
-    std::io::stdin().read_line(&mut s).unwrap_or_else(|_| {
-        vm.error("Could not read line");
-    });
+    if std::io::stdin().read_line(&mut s).is_err() {
+        return vm.error("Could not read line");
+    }
     s.pop();
     Value::String(s)
 }
 
 fn random(_: Vec<Value>, vm: &mut VM) -> Value {
-    let mut rng = rand::thread_rng();
-    Value::Number(rng.gen_range(0.0..1.0))
+    Value::Number(vm.rng().gen_range(0.0..1.0))
 }
 
-fn number(args: Vec<Value>, vm: &mut VM) -> Value {
+/// Reseeds the VM's generator: `seed(n)` makes subsequent `random`/
+/// `randomInt`/`randomRange`/`shuffle`/`choice` calls reproducible.
+fn seed(args: Vec<Value>, vm: &mut VM) -> Value {
     let mut args = args;
-    let s = if let Some(Value::String(s)) = args.pop() {
-        s
-    } else {
-        vm.error("First argument must be a string");
+    let n = match args.pop() {
+        Some(Value::Number(n)) => n,
+        _ => return Value::Error("seed() expects a number".to_string()),
+    };
+    vm.seed(n as u64);
+    Value::Nil
+}
+
+/// Inclusive-exclusive integer range: `randomInt(lo, hi)`.
+fn random_int(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let hi = match args.pop() {
+        Some(Value::Number(n)) => n as i64,
+        _ => return Value::Error("randomInt() expects numeric bounds".to_string()),
+    };
+    let lo = match args.pop() {
+        Some(Value::Number(n)) => n as i64,
+        _ => return Value::Error("randomInt() expects numeric bounds".to_string()),
+    };
+    if lo >= hi {
+        return Value::Error("randomInt() requires lo < hi".to_string());
+    }
+    Value::Number(vm.rng().gen_range(lo..hi) as f64)
+}
+
+/// Float range: `randomRange(lo, hi)`.
+fn random_range(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let hi = match args.pop() {
+        Some(Value::Number(n)) => n,
+        _ => return Value::Error("randomRange() expects numeric bounds".to_string()),
+    };
+    let lo = match args.pop() {
+        Some(Value::Number(n)) => n,
+        _ => return Value::Error("randomRange() expects numeric bounds".to_string()),
+    };
+    if lo >= hi {
+        return Value::Error("randomRange() requires lo < hi".to_string());
+    }
+    Value::Number(vm.rng().gen_range(lo..hi))
+}
+
+/// In-place Fisher-Yates shuffle over a `List`'s backing `Vec<Value>`. The
+/// working copy is pinned as a GC root for the swap loop even though
+/// shuffling itself never calls back into script code, to stay consistent
+/// with `list_sort`'s working copy -- a future change to either shouldn't
+/// have to remember which one is safe to leave unpinned.
+fn shuffle(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("shuffle() expects a list as its receiver".to_string()),
+    };
+    let mut items = vm.get_collectable::<List>(this).unwrap().items.clone();
+    for item in &items {
+        vm.pin_root(item.clone());
+    }
+    let len = items.len();
+    let mut i = len;
+    while i > 1 {
+        i -= 1;
+        let j = vm.rng().gen_range(0..=i);
+        items.swap(i, j);
+    }
+    vm.unpin_roots(len);
+    vm.get_collectable_mut::<List>(this).unwrap().items = items;
+    Value::List(this)
+}
+
+/// Returns a uniformly chosen element of a `List`, or the error value for an
+/// empty list.
+fn choice(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("choice() expects a list as its receiver".to_string()),
+    };
+    let len = vm.get_collectable::<List>(this).unwrap().items.len();
+    if len == 0 {
+        return Value::Error("choice() called on an empty list".to_string());
+    }
+    let index = vm.rng().gen_range(0..len);
+    vm.get_collectable::<List>(this).unwrap().items[index].clone()
+}
+
+fn number(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    let s = match args.pop() {
+        Some(Value::String(s)) => s,
+        _ => return Value::Error("number() expects a string argument".to_string()),
     };
     if let Ok(number) = s.parse::<f64>() {
         return Value::Number(number);
     }
-    Value::Nil
+    Value::Error(format!("could not parse '{}' as a number", s))
 }
 
-fn int(args: Vec<Value>, vm: &mut VM) -> Value {
+fn int(args: Vec<Value>, _vm: &mut VM) -> Value {
     let mut args = args;
-    let s = if let Some(Value::String(s)) = args.pop() {
-        s
-    } else {
-        vm.error("First argument must be a string");
+    let s = match args.pop() {
+        Some(Value::String(s)) => s,
+        _ => return Value::Error("int() expects a string argument".to_string()),
     };
     if let Ok(number) = s.parse::<i32>() {
         return Value::Number(number as f64);
     }
-    Value::Nil
+    Value::Error(format!("could not parse '{}' as an int", s))
 }
 
-fn floor(args: Vec<Value>, vm: &mut VM) -> Value {
+fn floor(args: Vec<Value>, _vm: &mut VM) -> Value {
     let mut args = args;
-    let number = if let Some(Value::Number(number)) = args.pop() {
-        number
-    } else {
-        vm.error("First argument must be a number");
+    let number = match args.pop() {
+        Some(Value::Number(number)) => number,
+        _ => return Value::Error("floor() expects a number argument".to_string()),
     };
     Value::Number(number.floor())
 }
 
+/// Pops and validates a single numeric argument, naming `name` in the error.
+fn one_number_arg(args: &mut Vec<Value>, name: &str) -> Result<f64, Value> {
+    match args.pop() {
+        Some(Value::Number(n)) => Ok(n),
+        _ => Err(Value::Error(format!("{}() expects a number argument", name))),
+    }
+}
+
+fn ceil(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "ceil") {
+        Ok(n) => Value::Number(n.ceil()),
+        Err(error) => error,
+    }
+}
+
+fn round(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "round") {
+        Ok(n) => Value::Number(n.round()),
+        Err(error) => error,
+    }
+}
+
+fn trunc(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "trunc") {
+        Ok(n) => Value::Number(n.trunc()),
+        Err(error) => error,
+    }
+}
+
+fn abs(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "abs") {
+        Ok(n) => Value::Number(n.abs()),
+        Err(error) => error,
+    }
+}
+
+fn sqrt(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "sqrt") {
+        Ok(n) => Value::Number(n.sqrt()),
+        Err(error) => error,
+    }
+}
+
+fn pow(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    let y = match one_number_arg(&mut args, "pow") {
+        Ok(n) => n,
+        Err(error) => return error,
+    };
+    let x = match one_number_arg(&mut args, "pow") {
+        Ok(n) => n,
+        Err(error) => return error,
+    };
+    Value::Number(x.powf(y))
+}
+
+fn log(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "log") {
+        Ok(n) => Value::Number(n.ln()),
+        Err(error) => error,
+    }
+}
+
+fn exp(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "exp") {
+        Ok(n) => Value::Number(n.exp()),
+        Err(error) => error,
+    }
+}
+
+fn sin(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "sin") {
+        Ok(n) => Value::Number(n.sin()),
+        Err(error) => error,
+    }
+}
+
+fn cos(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "cos") {
+        Ok(n) => Value::Number(n.cos()),
+        Err(error) => error,
+    }
+}
+
+fn tan(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "tan") {
+        Ok(n) => Value::Number(n.tan()),
+        Err(error) => error,
+    }
+}
+
+fn asin(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "asin") {
+        Ok(n) => Value::Number(n.asin()),
+        Err(error) => error,
+    }
+}
+
+fn acos(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "acos") {
+        Ok(n) => Value::Number(n.acos()),
+        Err(error) => error,
+    }
+}
+
+fn atan(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    match one_number_arg(&mut args, "atan") {
+        Ok(n) => Value::Number(n.atan()),
+        Err(error) => error,
+    }
+}
+
+/// Variadic fold used by `min`/`max` over their numeric arguments.
+fn variadic_fold(args: Vec<Value>, fold: fn(f64, f64) -> f64, name: &str) -> Value {
+    if args.is_empty() {
+        return Value::Error(format!("{}() expects at least one argument", name));
+    }
+    let mut numbers = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg {
+            Value::Number(n) => numbers.push(n),
+            _ => return Value::Error(format!("{}() expects only number arguments", name)),
+        }
+    }
+    Value::Number(numbers.into_iter().reduce(fold).unwrap())
+}
+
+fn min(args: Vec<Value>, _vm: &mut VM) -> Value {
+    variadic_fold(args, f64::min, "min")
+}
+
+fn max(args: Vec<Value>, _vm: &mut VM) -> Value {
+    variadic_fold(args, f64::max, "max")
+}
+
 fn panic(args: Vec<Value>, vm: &mut VM) -> Value {
     let mut args = args;
-    let message = if let Some(Value::String(message)) = args.pop() {
-        message
-    } else {
-        vm.error("First argument must be a string");
+    let message = match args.pop() {
+        Some(Value::String(message)) => message,
+        _ => return vm.error("First argument must be a string"),
+    };
+    vm.error(message)
+}
+
+/// Constructs a recoverable error value: `error("message")`.
+fn error(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    let message = match args.pop() {
+        Some(Value::String(message)) => message,
+        _ => "error() expects a string message".to_string(),
+    };
+    Value::Error(message)
+}
+
+/// Predicate pairing with `error`: `isError(x)`.
+fn is_error(args: Vec<Value>, _vm: &mut VM) -> Value {
+    Value::Boolean(matches!(args.get(0), Some(Value::Error(_))))
+}
+
+/// Runs a zero-arg function/closure and catches any fatal error it raises,
+/// returning either its result or the error value instead of aborting.
+fn try_fn(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let callee = match args.pop() {
+        Some(callee) => callee,
+        None => return Value::Error("try() expects a function argument".to_string()),
+    };
+    match vm.call_reentrant(callee, Vec::new()) {
+        Ok(value) => value,
+        Err(runtime_error) => runtime_error.into_value(),
+    }
+}
+
+/// Compiles and runs a Horst source string on the current VM: `eval(source)`.
+fn eval(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let source = match args.pop() {
+        Some(Value::String(source)) => source,
+        _ => return Value::Error("eval() expects a source string".to_string()),
+    };
+    vm.eval_source(&source)
+}
+
+/// Calls `fn` with the elements of `list` as positional arguments:
+/// `apply(fn, list)`.
+fn apply(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    if args.len() < 2 {
+        return Value::Error("apply() expects a function and a list of arguments".to_string());
+    }
+    let list = args.pop().unwrap();
+    let callee = args.pop().unwrap();
+    let elements = match list {
+        Value::List(list_ref) => vm.get_collectable::<List>(list_ref).unwrap().items.clone(),
+        _ => return Value::Error("apply() expects a list as its second argument".to_string()),
+    };
+    vm.call_reentrant(callee, elements).unwrap_or_else(|error| error.into_value())
+}
+
+/// Wraps `fn` in a suspended fiber that hasn't started running yet:
+/// `create(fn)`. Resume it with `resume(fiber)` to begin execution.
+fn create(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let callee = match args.pop() {
+        Some(callee @ (Value::Closure(..) | Value::Function(..))) => callee,
+        _ => return Value::Error("create() expects a function argument".to_string()),
+    };
+    vm.new_fiber(callee)
+}
+
+/// Resumes a suspended fiber: `resume(fiber)` or `resume(fiber, value)`.
+/// `value` becomes the fiber's starting argument on its first resume, or
+/// what its paused `yield` expression evaluates to on later resumes.
+/// Returns the fiber's next yielded value, or its return value once it runs
+/// to completion.
+fn resume(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let value = if args.len() > 1 { args.pop().unwrap() } else { Value::Nil };
+    let fiber_id = match args.pop() {
+        Some(Value::Fiber(id)) => id,
+        _ => return Value::Error("resume() expects a fiber as its first argument".to_string()),
+    };
+    match vm.resume_fiber(fiber_id, value) {
+        Ok(result) => result,
+        Err(error) => vm.propagate(error),
+    }
+}
+
+fn read_file(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    let path = match args.pop() {
+        Some(Value::String(path)) => path,
+        _ => return Value::Error("readFile() expects a path string".to_string()),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Value::String(contents),
+        Err(err) => Value::Error(format!("could not read '{}': {}", path, err)),
+    }
+}
+
+fn write_file(args: Vec<Value>, _vm: &mut VM) -> Value {
+    let mut args = args;
+    let contents = match args.pop() {
+        Some(Value::String(contents)) => contents,
+        _ => return Value::Error("writeFile() expects a contents string".to_string()),
+    };
+    let path = match args.pop() {
+        Some(Value::String(path)) => path,
+        _ => return Value::Error("writeFile() expects a path string".to_string()),
     };
-    vm.error(message);
+    match std::fs::write(&path, contents) {
+        Ok(()) => Value::Nil,
+        Err(err) => Value::Error(format!("could not write '{}': {}", path, err)),
+    }
+}
+
+/// Parses the process argv into a `Map` of flags, using the simple argmap
+/// convention: `--key value` / `--key=value` / bare `--key` (true) / a short
+/// cluster `-abc` (sets `a`, `b`, `c` to true); everything else is
+/// positional. The positional arguments are exposed as a `List` under the
+/// reserved key `"_"` in that same map.
+fn args_fn(_: Vec<Value>, vm: &mut VM) -> Value {
+    let map_class = match vm.get_global_by_name("Map") {
+        Some(Value::Class(class)) => class,
+        _ => return Value::Error("args() requires the Map class to be registered".to_string()),
+    };
+    let list_class = match vm.get_global_by_name("List") {
+        Some(Value::Class(class)) => class,
+        _ => return Value::Error("args() requires the List class to be registered".to_string()),
+    };
+
+    let mut flags = Instance::new(map_class);
+    let mut positional = Vec::new();
+
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let mut i = 0;
+    while i < argv.len() {
+        let token = &argv[i];
+        if let Some(rest) = token.strip_prefix("--") {
+            if let Some((key, value)) = rest.split_once('=') {
+                flags.fields.insert(key.to_string(), Value::String(value.to_string()));
+            } else if argv.get(i + 1).is_some_and(|next| !next.starts_with('-')) {
+                flags.fields.insert(rest.to_string(), Value::String(argv[i + 1].clone()));
+                i += 1;
+            } else {
+                flags.fields.insert(rest.to_string(), Value::Boolean(true));
+            }
+        } else if let Some(rest) = token.strip_prefix('-').filter(|rest| !rest.is_empty()) {
+            for flag in rest.chars() {
+                flags.fields.insert(flag.to_string(), Value::Boolean(true));
+            }
+        } else {
+            positional.push(Value::String(token.clone()));
+        }
+        i += 1;
+    }
+
+    let list_id = vm.new_collectable(List::new(list_class, positional));
+    flags.fields.insert("_".to_string(), Value::List(list_id));
+
+    vm.new_instance(flags)
 }
 
 fn make_map() -> Class {
@@ -92,38 +550,40 @@ fn make_map() -> Class {
     Class {
         name: "Map".to_string(),
         methods,
+        foreign: None,
+        is_list: false,
     }
 }
 
 fn map_get(args: Vec<Value>, vm: &mut VM) -> Value {
     let mut args = args;
     let map = if let Value::Instance(map) = args.remove(0) {
-        vm.gc.deref(map)
+        vm.get_instance(map).unwrap()
     } else {
-        panic!("First argument must be a map");
+        return Value::Error("Map.get() expects a map as its receiver".to_string());
     };
     let key = if let Value::String(key) = args.remove(0) {
         key
     } else {
-        panic!("Second argument must be a string");
+        return Value::Error("Map.get() expects a string key".to_string());
     };
     map.fields.get(&key).unwrap_or(&Value::Nil).clone()
 }
 
 fn map_set(args: Vec<Value>, vm: &mut VM) -> Value {
-    println!("{:?}", args);
     let mut args = args;
-    let mut map = if let Value::Instance(map) = args.remove(0) {
-        vm.gc.deref_mut(map)
+    let map_ref = if let Value::Instance(map) = args.remove(0) {
+        map
     } else {
-        panic!("First argument must be a map");
+        return Value::Error("Map.set() expects a map as its receiver".to_string());
     };
     let key = if let Value::String(key) = args.remove(0) {
         key
     } else {
-        panic!("Second argument must be a string");
+        return Value::Error("Map.set() expects a string key".to_string());
     };
     let value = args.pop().unwrap();
+    let map = vm.get_instance_mut(map_ref).unwrap();
     map.fields.insert(key, value);
     Value::Nil
 }
@@ -131,9 +591,9 @@ fn map_set(args: Vec<Value>, vm: &mut VM) -> Value {
 fn map_to_string(args: Vec<Value>, vm: &mut VM) -> Value {
     let mut args = args;
     let map = if let Value::Instance(map) = args.pop().unwrap() {
-        vm.gc.deref(map)
+        vm.get_instance(map).unwrap()
     } else {
-        panic!("First argument must be a map");
+        return Value::Error("Map.toString() expects a map as its receiver".to_string());
     };
     let mut s = "{".to_string();
     for (i, (key, value)) in map.fields.iter().enumerate() {
@@ -148,4 +608,346 @@ fn map_to_string(args: Vec<Value>, vm: &mut VM) -> Value {
     }
     s.push('}');
     Value::String(s)
+}
+
+/// Unlike `Map`, `List` is backed by a real `Vec<Value>` (see `vm::List`)
+/// rather than `Instance`'s stringified-index fields, so indexing/length/
+/// push/pop/sort are direct `Vec` operations instead of `HashMap` lookups.
+/// `List()` is special-cased in `VM::call_value` (via `Class::is_list`) to
+/// allocate an empty `vm::List` rather than an `Instance`.
+fn make_list() -> Class {
+    let mut methods = HashMap::new();
+    methods.insert("push".to_string(), Value::NativeFunction(NativeFunction { function: list_push }));
+    methods.insert("pop".to_string(), Value::NativeFunction(NativeFunction { function: list_pop }));
+    methods.insert("get".to_string(), Value::NativeFunction(NativeFunction { function: list_get }));
+    methods.insert("set".to_string(), Value::NativeFunction(NativeFunction { function: list_set }));
+    methods.insert("length".to_string(), Value::NativeFunction(NativeFunction { function: list_length }));
+    methods.insert("map".to_string(), Value::NativeFunction(NativeFunction { function: list_map }));
+    methods.insert("filter".to_string(), Value::NativeFunction(NativeFunction { function: list_filter }));
+    methods.insert("forEach".to_string(), Value::NativeFunction(NativeFunction { function: list_for_each }));
+    methods.insert("sort".to_string(), Value::NativeFunction(NativeFunction { function: list_sort }));
+    methods.insert("toString".to_string(), Value::NativeFunction(NativeFunction { function: list_to_string }));
+    Class {
+        name: "List".to_string(),
+        methods,
+        foreign: None,
+        is_list: true,
+    }
+}
+
+fn list_push(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let value = args.pop().unwrap_or(Value::Nil);
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("push() expects a list as its receiver".to_string()),
+    };
+    vm.get_collectable_mut::<List>(this).unwrap().items.push(value);
+    Value::List(this)
+}
+
+fn list_pop(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("pop() expects a list as its receiver".to_string()),
+    };
+    match vm.get_collectable_mut::<List>(this).unwrap().items.pop() {
+        Some(value) => value,
+        None => Value::Error("pop() called on an empty list".to_string()),
+    }
+}
+
+fn list_get(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let index = match args.pop() {
+        Some(Value::Number(n)) => n as usize,
+        _ => return Value::Error("get() expects a numeric index".to_string()),
+    };
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("get() expects a list as its receiver".to_string()),
+    };
+    vm.get_collectable::<List>(this).unwrap().items.get(index).cloned().unwrap_or(Value::Nil)
+}
+
+/// Writes `value` at `index`, growing the list with `Nil` padding first if
+/// `index` is past the current end -- so `length()`/`toString`/`map` never
+/// see a sparse hole the way they would if `set` silently no-opped instead.
+fn list_set(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let value = args.pop().unwrap_or(Value::Nil);
+    let index = match args.pop() {
+        Some(Value::Number(n)) => n as usize,
+        _ => return Value::Error("set() expects a numeric index".to_string()),
+    };
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("set() expects a list as its receiver".to_string()),
+    };
+    let items = &mut vm.get_collectable_mut::<List>(this).unwrap().items;
+    if index >= items.len() {
+        items.resize(index + 1, Value::Nil);
+    }
+    items[index] = value;
+    Value::Nil
+}
+
+fn list_length(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("length() expects a list as its receiver".to_string()),
+    };
+    Value::Number(vm.get_collectable::<List>(this).unwrap().items.len() as f64)
+}
+
+fn list_map(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let callback = match args.pop() {
+        Some(callback) => callback,
+        None => return Value::Error("map() expects a function argument".to_string()),
+    };
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("map() expects a list as its receiver".to_string()),
+    };
+    let list = vm.get_collectable::<List>(this).unwrap();
+    let class = list.class();
+    let items = list.items.clone();
+    // Each mapped value is only a bare `Value` in a Rust-local `Vec` until
+    // it's wrapped in the result `List` below, so it isn't reachable from
+    // any root `collect_garbage` knows about; pin it so a collection
+    // triggered by a later callback call can't free it out from under us.
+    let mut mapped = Vec::with_capacity(items.len());
+    for item in items {
+        let value = vm.call_reentrant(callback.clone(), vec![item])
+            .unwrap_or_else(|error| error.into_value());
+        vm.pin_root(value.clone());
+        mapped.push(value);
+    }
+    let pinned = mapped.len();
+    let result = vm.new_collectable(List::new(class, mapped));
+    vm.unpin_roots(pinned);
+    Value::List(result)
+}
+
+fn list_filter(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let predicate = match args.pop() {
+        Some(predicate) => predicate,
+        None => return Value::Error("filter() expects a function argument".to_string()),
+    };
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("filter() expects a list as its receiver".to_string()),
+    };
+    let list = vm.get_collectable::<List>(this).unwrap();
+    let class = list.class();
+    let items = list.items.clone();
+    let mut kept = Vec::new();
+    for item in items {
+        let kept_value = vm.call_reentrant(predicate.clone(), vec![item.clone()])
+            .unwrap_or_else(|error| error.into_value());
+        if !kept_value.is_falsey() {
+            // See `list_map`: not reachable from any root until it lands
+            // in the result `List`, so pin it across the remaining calls.
+            vm.pin_root(item.clone());
+            kept.push(item);
+        }
+    }
+    let pinned = kept.len();
+    let result = vm.new_collectable(List::new(class, kept));
+    vm.unpin_roots(pinned);
+    Value::List(result)
+}
+
+fn list_for_each(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let callback = match args.pop() {
+        Some(callback) => callback,
+        None => return Value::Error("forEach() expects a function argument".to_string()),
+    };
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("forEach() expects a list as its receiver".to_string()),
+    };
+    let items = vm.get_collectable::<List>(this).unwrap().items.clone();
+    for item in items {
+        let _ = vm.call_reentrant(callback.clone(), vec![item]);
+    }
+    Value::Nil
+}
+
+/// Default ordering used by `sort()` when no comparator is given.
+fn default_compare(a: &Value, b: &Value) -> std::cmp::Ordering {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        _ => std::cmp::Ordering::Equal,
+    }
+}
+
+fn compare(vm: &mut VM, a: &Value, b: &Value, cmp: &Option<Value>) -> std::cmp::Ordering {
+    match cmp {
+        Some(f) => match vm.call_reentrant(f.clone(), vec![a.clone(), b.clone()])
+            .unwrap_or_else(|error| error.into_value()) {
+            Value::Number(n) if n < 0.0 => std::cmp::Ordering::Less,
+            Value::Number(n) if n > 0.0 => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        },
+        None => default_compare(a, b),
+    }
+}
+
+/// Merges the sorted runs `[start, mid)` and `[mid, end)` of `src` into `dst`.
+fn merge(vm: &mut VM, src: &[Value], dst: &mut [Value], start: usize, mid: usize, end: usize, cmp: &Option<Value>) {
+    let (mut i, mut j) = (start, mid);
+    for slot in dst.iter_mut().take(end).skip(start) {
+        let take_left = if i < mid && j < end {
+            compare(vm, &src[i], &src[j], cmp) != std::cmp::Ordering::Greater
+        } else {
+            i < mid
+        };
+        if take_left {
+            *slot = src[i].clone();
+            i += 1;
+        } else {
+            *slot = src[j].clone();
+            j += 1;
+        }
+    }
+}
+
+/// Bottom-up (iterative) merge sort: stable, and avoids recursion depth
+/// proportional to list length. Repeatedly merges adjacent runs of width
+/// 1, 2, 4, ... between `items` and a scratch buffer, swapping roles each
+/// pass.
+fn merge_sort(vm: &mut VM, items: &mut Vec<Value>, cmp: Option<Value>) {
+    let len = items.len();
+    if len < 2 {
+        return;
+    }
+    let mut buffer = items.clone();
+    let mut width = 1;
+    let mut items_is_source = true;
+    while width < len {
+        {
+            let (src, dst): (&mut Vec<Value>, &mut Vec<Value>) = if items_is_source {
+                (items, &mut buffer)
+            } else {
+                (&mut buffer, items)
+            };
+            let mut start = 0;
+            while start < len {
+                let mid = (start + width).min(len);
+                let end = (start + 2 * width).min(len);
+                merge(vm, src, dst, start, mid, end, &cmp);
+                start += 2 * width;
+            }
+        }
+        width *= 2;
+        items_is_source = !items_is_source;
+    }
+    if !items_is_source {
+        *items = buffer;
+    }
+}
+
+fn list_sort(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let cmp = if args.len() > 1 { args.pop() } else { None };
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("sort() expects a list as its receiver".to_string()),
+    };
+    let mut items = vm.get_collectable::<List>(this).unwrap().items.clone();
+    // The comparator runs arbitrary script code via `call_reentrant`, which
+    // can allocate and trigger a collection; pin the working copy (and
+    // `merge_sort`'s internal scratch buffer, which starts as a clone of
+    // it) for the duration of the sort.
+    for item in &items {
+        vm.pin_root(item.clone());
+    }
+    let pinned = items.len();
+    merge_sort(vm, &mut items, cmp);
+    vm.unpin_roots(pinned);
+    vm.get_collectable_mut::<List>(this).unwrap().items = items;
+    Value::List(this)
+}
+
+fn list_to_string(args: Vec<Value>, vm: &mut VM) -> Value {
+    let mut args = args;
+    let this = match args.pop() {
+        Some(Value::List(this)) => this,
+        _ => return Value::Error("toString() expects a list as its receiver".to_string()),
+    };
+    let items = &vm.get_collectable::<List>(this).unwrap().items;
+    let mut s = "[".to_string();
+    for (i, value) in items.iter().enumerate() {
+        if i > 0 {
+            s.push_str(", ");
+        }
+        match value {
+            Value::String(value) => s.push_str(&format!("\"{}\"", value)),
+            value => s.push_str(&format!("{}", value)),
+        }
+    }
+    s.push(']');
+    Value::String(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_sort_orders_ascending() {
+        let mut vm = VM::new();
+        let items = vec![Value::Number(3.0), Value::Number(1.0), Value::Number(2.0)];
+        let id = vm.new_collectable(List::new(0, items));
+
+        let result = list_sort(vec![Value::List(id)], &mut vm);
+
+        assert_eq!(result, Value::List(id));
+        assert_eq!(
+            vm.get_collectable::<List>(id).unwrap().items,
+            vec![Value::Number(1.0), Value::Number(2.0), Value::Number(3.0)]
+        );
+    }
+
+    #[test]
+    fn shuffle_permutes_without_losing_or_duplicating_elements() {
+        let mut vm = VM::new();
+        vm.seed(42);
+        let original = vec![
+            Value::Number(1.0),
+            Value::Number(2.0),
+            Value::Number(3.0),
+            Value::Number(4.0),
+            Value::Number(5.0),
+        ];
+        let id = vm.new_collectable(List::new(0, original.clone()));
+
+        shuffle(vec![Value::List(id)], &mut vm);
+
+        let mut shuffled = vm.get_collectable::<List>(id).unwrap().items.clone();
+        let mut expected = original;
+        shuffled.sort_by(default_compare);
+        expected.sort_by(default_compare);
+        assert_eq!(shuffled, expected);
+    }
+
+    #[test]
+    fn try_fn_catches_a_native_error_instead_of_propagating() {
+        let mut vm = VM::new();
+        // `panic` raises via `vm.error(...)` when called with no arguments,
+        // which is exactly the kind of fatal `RuntimeError` `try_fn` is
+        // meant to intercept and turn into an observable `Value::Error`.
+        let raiser = Value::NativeFunction(NativeFunction { function: panic });
+
+        let result = try_fn(vec![raiser], &mut vm);
+
+        assert_eq!(result, Value::Error("First argument must be a string".to_string()));
+    }
 }
\ No newline at end of file
@@ -1,18 +1,32 @@
 use std::any::Any;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::marker::PhantomData;
 use std::os::unix::process::parent_id;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use crate::class::{Class, ClassRef};
 use crate::frame::CallFrame;
-use crate::function::Function;
+use crate::function::{Function, NativeFunction};
 use crate::instance::Instance;
 use crate::instruction::Instruction;
 use crate::value::{InstanceRef, UpvalueRegistryRef, Value};
 
+/// Below this many live objects we never bother collecting -- not worth the
+/// trace pass for a script that barely allocates.
+const MIN_HEAP_THRESHOLD: usize = 256;
+/// After a collection, the next one doesn't fire until the heap has grown by
+/// this factor again, so GC frequency scales with how much the program
+/// actually keeps alive rather than with raw allocation count.
+const HEAP_GROW_FACTOR: usize = 2;
+
 struct Heap {
     objects: HashMap<usize, Box<dyn Collectable>>,
     next_id: usize,
+    next_gc: usize,
 }
 
 
@@ -21,6 +35,7 @@ impl Heap {
         Heap {
             objects: HashMap::new(),
             next_id: 0,
+            next_gc: MIN_HEAP_THRESHOLD,
         }
     }
 }
@@ -31,6 +46,150 @@ pub trait Collectable: Any {
     fn to_string(&self, _: &VM) -> Option<String> {
         None
     }
+
+    /// Pushes every heap id this object keeps alive onto `worklist`, so
+    /// `VM::collect_garbage` can mark everything reachable from the roots.
+    /// `Instance` traces its fields and class id, `Class` traces its
+    /// methods, a closed upvalue traces its value; objects with no outgoing
+    /// references can rely on this empty default.
+    fn trace(&self, _worklist: &mut Vec<usize>) {}
+}
+
+/// Pushes the heap ids `value` itself references (as opposed to ids nested
+/// inside objects already on the heap, which is what `Collectable::trace`
+/// is for) onto `worklist`. Shared by GC root-scanning and by `Collectable`
+/// impls that hold `Value`s directly, like a closed upvalue.
+fn trace_value(value: &Value, worklist: &mut Vec<usize>) {
+    match value {
+        Value::Instance(id) => worklist.push(*id),
+        Value::Class(id) => worklist.push(*id),
+        Value::Closure(_, upvalues) => worklist.extend(upvalues.iter().copied()),
+        Value::BoundMethod { receiver, upvalues, .. } => {
+            worklist.push(*receiver);
+            worklist.extend(upvalues.iter().copied());
+        }
+        Value::Fiber(id) => worklist.push(*id),
+        Value::Foreign(id) => worklist.push(*id),
+        Value::List(id) => worklist.push(*id),
+        _ => {}
+    }
+}
+
+/// Builds the opaque Rust state behind a foreign object from its
+/// constructor arguments, e.g. opening a file handle or allocating a
+/// buffer. Registered per foreign class via `VM::define_foreign_class`;
+/// wrapped in `Rc` rather than `Box` so a `Class` (which natives `.clone()`
+/// freely, same as its script methods) stays cheaply cloneable.
+pub type ForeignAllocator = Rc<dyn Fn(Vec<Value>, &mut VM) -> Box<dyn Any>>;
+
+/// A host-backed object: opaque Rust state owned by a foreign class's
+/// allocator. Its class's native methods downcast `data` (via
+/// `get_collectable::<Foreign>` then `data.downcast_ref::<T>()`) to reach
+/// it, the same way `Instance` methods reach `fields`.
+pub struct Foreign {
+    class: ClassRef,
+    pub data: Box<dyn Any>,
+}
+
+impl Collectable for Foreign {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn trace(&self, worklist: &mut Vec<usize>) {
+        worklist.push(self.class);
+    }
+}
+
+/// Backing store for the `List` native class: a real `Vec<Value>`, so
+/// indexing/length/push/pop/sort all operate directly on it instead of
+/// going through `Map`'s stringified-index-plus-"length" `Instance` fields.
+/// Its `class` is traced like any other method table; `items` are traced
+/// like the value stack, since they're ordinary script values the list
+/// happens to own.
+pub struct List {
+    class: ClassRef,
+    pub items: Vec<Value>,
+}
+
+impl List {
+    pub fn new(class: ClassRef, items: Vec<Value>) -> List {
+        List { class, items }
+    }
+
+    pub fn class(&self) -> ClassRef {
+        self.class
+    }
+}
+
+impl Collectable for List {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn trace(&self, worklist: &mut Vec<usize>) {
+        worklist.push(self.class);
+        for value in &self.items {
+            trace_value(value, worklist);
+        }
+    }
+}
+
+/// A runtime failure propagated as a `Result` instead of unwinding the
+/// process: a plain message (VM-internal failures like a type mismatch or
+/// an undefined global), a script value raised by `Throw`, or a
+/// host-initiated cancellation (`Interrupted`/`BudgetExhausted`).
+#[derive(Clone, Debug)]
+pub enum RuntimeError {
+    Message(String),
+    Thrown(Value),
+    /// The host's `interrupt` handle was set; raised at the next
+    /// instruction boundary regardless of any active `try` block.
+    Interrupted,
+    /// `interpret_with_budget`'s instruction counter reached zero; raised
+    /// the same way as `Interrupted`.
+    BudgetExhausted,
+}
+
+impl RuntimeError {
+    fn message(message: impl Into<String>) -> RuntimeError {
+        RuntimeError::Message(message.into())
+    }
+
+    /// Whether a script-level `try` block is allowed to catch this error.
+    /// Host-initiated cancellation (`Interrupted`/`BudgetExhausted`) must
+    /// not be swallowed by the script's own error handling, or it would
+    /// defeat the point of a forced cancellation.
+    fn is_catchable(&self) -> bool {
+        !matches!(self, RuntimeError::Interrupted | RuntimeError::BudgetExhausted)
+    }
+
+    /// The value a `try_frames` handler sees: a thrown value as-is, a plain
+    /// message wrapped as the recoverable `Value::Error`.
+    pub(crate) fn into_value(self) -> Value {
+        match self {
+            RuntimeError::Thrown(value) => value,
+            RuntimeError::Message(message) => Value::Error(message),
+            RuntimeError::Interrupted => Value::Error("Interrupted.".to_string()),
+            RuntimeError::BudgetExhausted => Value::Error("Budget exhausted.".to_string()),
+        }
+    }
+}
+
+/// One active `try` block on a `CallFrame`: where to jump and how far to
+/// unwind the stack if the protected region raises.
+#[derive(Clone, Copy, Debug)]
+pub struct TryFrame {
+    pub catch_ip: usize,
+    pub stack_len: usize,
 }
 
 pub struct VM {
@@ -39,6 +198,36 @@ pub struct VM {
     globals: HashMap<String, Value>,
     open_upvalues: Vec<UpvalueRegistryRef>,
     heap: Heap,
+    rng: StdRng,
+    last_result: Option<Value>,
+    /// Remaining instructions `step` may execute before raising
+    /// `RuntimeError::BudgetExhausted`. `None` means unbounded; set by
+    /// `interpret_with_budget`.
+    budget: Option<u64>,
+    /// Set from another thread via the handle returned by
+    /// `interrupt_handle()` to cooperatively cancel a running script.
+    interrupt: Arc<AtomicBool>,
+    /// Set by `Instruction::Yield` for the duration of the `step` call that
+    /// executed it; `resume_fiber`'s run loop takes it to know the fiber
+    /// suspended rather than returned.
+    pending_yield: Option<Value>,
+    /// The resumer's frames/stack, parked here for the duration of a nested
+    /// `resume_fiber` call while the fiber's own frames/stack occupy `self.
+    /// frames`/`self.stack`. `collect_garbage` traces these as roots too, so
+    /// a collection triggered by the fiber doesn't free anything still held
+    /// by the suspended outer call.
+    suspended: Vec<(Vec<CallFrame>, Vec<Value>)>,
+    /// Set by `error()` when a native function raises a fatal error. The
+    /// native still has to return some `Value` to satisfy `NativeFunction`'s
+    /// signature; `call_value`'s `Value::NativeFunction` arm checks this
+    /// afterwards and turns it into a proper `Err(RuntimeError)` so it flows
+    /// through the normal `unwind_to_try`/`try` path instead of panicking.
+    pending_native_error: Option<RuntimeError>,
+    /// Extra GC roots pinned by native functions via `pin_root`/`unpin_roots`:
+    /// values held in a Rust-local temporary across a `call_reentrant` call
+    /// (a sort's working copy, a map/filter's in-progress results, ...) that
+    /// aren't yet reachable from the stack, globals, or any frame.
+    native_roots: Vec<Value>,
 }
 
 impl VM {
@@ -49,17 +238,296 @@ impl VM {
             globals: HashMap::new(),
             open_upvalues: Vec::new(),
             heap: Heap::new(),
+            rng: StdRng::from_entropy(),
+            last_result: None,
+            budget: None,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            pending_yield: None,
+            suspended: Vec::new(),
+            pending_native_error: None,
+            native_roots: Vec::new(),
         }
     }
 
-    pub fn interpret(&mut self, function: Function) {
+    /// Pins `value` as an extra GC root until a matching `unpin_roots` call
+    /// releases it. For a native function that holds a just-produced `Value`
+    /// in a Rust-local temporary across a `call_reentrant` call -- which can
+    /// trigger a collection -- that isn't yet reachable from the stack,
+    /// globals, or any frame.
+    pub fn pin_root(&mut self, value: Value) {
+        self.native_roots.push(value);
+    }
+
+    /// Unpins the `count` most recently pinned roots, in LIFO order with
+    /// `pin_root`.
+    pub fn unpin_roots(&mut self, count: usize) {
+        let new_len = self.native_roots.len().saturating_sub(count);
+        self.native_roots.truncate(new_len);
+    }
+
+    /// Returns a cloneable handle that another thread can set to cancel the
+    /// currently (or next) running script. Checked at the top of every
+    /// dispatch step; once set, the VM raises `RuntimeError::Interrupted` at
+    /// the next instruction boundary and stays cancelled until the flag is
+    /// cleared.
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    /// Reseeds the VM's random generator so `random`/`randomInt`/`shuffle`/
+    /// `choice` become reproducible across runs.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Looks up a global by name, e.g. so natives can reach the `Map`/`List`
+    /// classes to build instances of them.
+    pub fn get_global_by_name(&self, name: &str) -> Option<Value> {
+        self.globals.get(name).cloned()
+    }
+
+    pub fn interpret(&mut self, function: Function) -> Result<Value, RuntimeError> {
+        self.budget = None;
+        self.interpret_inner(function)
+    }
+
+    /// Like `interpret`, but caps execution to `budget` instructions,
+    /// raising `RuntimeError::BudgetExhausted` if the script doesn't finish
+    /// within that count. Lets a host (REPL, server) bound how much work a
+    /// single call can perform without needing a watchdog thread.
+    pub fn interpret_with_budget(&mut self, function: Function, budget: u64) -> Result<Value, RuntimeError> {
+        self.budget = Some(budget);
+        self.interpret_inner(function)
+    }
+
+    fn interpret_inner(&mut self, function: Function) -> Result<Value, RuntimeError> {
         let closure = Value::Closure(function, Vec::new());
         self.push(closure.clone());
-        self.call_value(closure, 0);
-        self.run();
+        self.call_value(closure, 0)?;
+        self.run()?;
+        Ok(self.last_result.take().unwrap_or(Value::Nil))
     }
 
-    fn run(&mut self) {
+    /// Compiles `source` through the crate's lexer/compiler and runs it on
+    /// this VM, sharing the current globals. Used by the `eval` native so
+    /// scripts can build and run code dynamically. Compile errors are
+    /// surfaced as the recoverable error value rather than aborting.
+    pub fn eval_source(&mut self, source: &str) -> Value {
+        let tokens = match crate::lexer::Lexer::new(source).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(message) => return Value::Error(message),
+        };
+        let function = match crate::compiler::Compiler::new(tokens).compile() {
+            Ok(function) => function,
+            Err(message) => return Value::Error(message),
+        };
+        match self.call_reentrant(Value::Closure(function, Vec::new()), Vec::new()) {
+            Ok(value) => value,
+            Err(error) => error.into_value(),
+        }
+    }
+
+    /// Raises a fatal runtime error from a native function. Natives call
+    /// this to build their return value (`return vm.error("...")`); the
+    /// real error is stashed in `pending_native_error` and picked up by
+    /// `call_value`'s `Value::NativeFunction` arm once the native returns,
+    /// which turns it into `Err(RuntimeError)` so it unwinds through the
+    /// same `unwind_to_try`/`try` path as a bytecode-level failure, instead
+    /// of panicking and leaving the frame/stack in a half-popped state.
+    pub fn error(&mut self, message: impl Into<String>) -> Value {
+        self.pending_native_error = Some(RuntimeError::message(message));
+        Value::Nil
+    }
+
+    /// Propagates a `RuntimeError` a native already holds (typically from
+    /// `resume_fiber`/`call_reentrant`) out of that native call, respecting
+    /// `is_catchable()`: a catchable error becomes the `Value::Error`/thrown
+    /// value a script `try` can see, while a non-catchable one (host
+    /// cancellation) is stashed via `pending_native_error` like `error()`
+    /// does, so it keeps unwinding instead of becoming an observable value.
+    pub(crate) fn propagate(&mut self, error: RuntimeError) -> Value {
+        if error.is_catchable() {
+            error.into_value()
+        } else {
+            self.pending_native_error = Some(error);
+            Value::Nil
+        }
+    }
+
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        while !self.frames.is_empty() {
+            if let Err(error) = self.step() {
+                if !error.is_catchable() {
+                    return Err(error);
+                }
+                self.unwind_to_try(error, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes `callee` with `args` to completion and returns its result,
+    /// re-entering the dispatch loop for the frame(s) it pushes. This is how
+    /// natives (`try`, list methods, ...) call back into script code without
+    /// driving their own copy of `run`.
+    pub(crate) fn call_reentrant(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let arg_count = args.len();
+        let target_depth = self.frames.len();
+        self.push(callee);
+        for arg in args {
+            self.push(arg);
+        }
+        self.call_value_from_stack(arg_count)?;
+        while self.frames.len() > target_depth {
+            if let Err(error) = self.step() {
+                if !error.is_catchable() {
+                    return Err(error);
+                }
+                self.unwind_to_try(error, target_depth)?;
+            }
+        }
+        Ok(self.pop())
+    }
+
+    /// Unwinds frames (above `floor`) looking for an active `try_frames`
+    /// entry. If found, truncates the stack to its `stack_len`, jumps the
+    /// owning frame's `ip` to `catch_ip`, and pushes the error value so
+    /// script-level `catch` code sees it. If none is found down to `floor`,
+    /// the frames above `floor` are left popped and the error propagates.
+    fn unwind_to_try(&mut self, error: RuntimeError, floor: usize) -> Result<(), RuntimeError> {
+        while self.frames.len() > floor {
+            if let Some(try_frame) = self.frames.last_mut().unwrap().try_frames.pop() {
+                let frame = self.frames.last_mut().unwrap();
+                frame.ip = try_frame.catch_ip;
+                self.stack.truncate(try_frame.stack_len);
+                self.push(error.into_value());
+                return Ok(());
+            }
+            self.frames.pop();
+        }
+        Err(error)
+    }
+
+    /// Wraps `closure` in a new, not-yet-started fiber: `create(fn)`.
+    /// `resume` starts it on its first call.
+    pub fn new_fiber(&mut self, closure: Value) -> Value {
+        let fiber = Fiber {
+            state: FiberState::Suspended,
+            closure: Some(closure),
+            frames: Vec::new(),
+            stack: Vec::new(),
+        };
+        Value::Fiber(self.new_collectable(fiber))
+    }
+
+    /// Resumes a suspended fiber, swapping its saved frames/stack in for
+    /// the duration of the call and driving `step` directly (rather than
+    /// `run`) so a yield can be told apart from a normal return. `value` is
+    /// either the fiber's sole starting argument (first resume) or what its
+    /// paused `yield` expression evaluates to (later resumes).
+    ///
+    /// A non-catchable error (`Interrupted`/`BudgetExhausted`) is re-raised
+    /// to the caller instead of being turned into a `Value::Error` the
+    /// script could observe and swallow -- the same rule `run`/
+    /// `call_reentrant` apply via `is_catchable()`, just enforced here since
+    /// the fiber's step loop doesn't go through either of them.
+    pub fn resume_fiber(&mut self, fiber_id: usize, value: Value) -> Result<Value, RuntimeError> {
+        let (state, closure, frames, stack) = match self.get_collectable_mut::<Fiber>(fiber_id) {
+            Some(fiber) => (
+                fiber.state,
+                fiber.closure.take(),
+                std::mem::take(&mut fiber.frames),
+                std::mem::take(&mut fiber.stack),
+            ),
+            None => return Ok(Value::Error("resume() expects a fiber".to_string())),
+        };
+
+        match state {
+            FiberState::Done => return Ok(Value::Error("Cannot resume a finished fiber.".to_string())),
+            FiberState::Running => return Ok(Value::Error("Fiber is already running.".to_string())),
+            FiberState::Suspended => {}
+        }
+
+        if let Some(fiber) = self.get_collectable_mut::<Fiber>(fiber_id) {
+            fiber.state = FiberState::Running;
+        }
+
+        self.suspended.push((std::mem::replace(&mut self.frames, frames), std::mem::replace(&mut self.stack, stack)));
+        let starting_fresh = self.frames.is_empty();
+
+        if starting_fresh {
+            let closure = match closure {
+                Some(closure) => closure,
+                None => {
+                    let (outer_frames, outer_stack) = self.suspended.pop().unwrap();
+                    self.frames = outer_frames;
+                    self.stack = outer_stack;
+                    return Ok(Value::Error("Fiber has no function to run.".to_string()));
+                }
+            };
+            self.push(closure);
+            self.push(value);
+            if let Err(error) = self.call_value_from_stack(1) {
+                let (outer_frames, outer_stack) = self.suspended.pop().unwrap();
+                self.frames = outer_frames;
+                self.stack = outer_stack;
+                if let Some(fiber) = self.get_collectable_mut::<Fiber>(fiber_id) {
+                    fiber.state = FiberState::Done;
+                }
+                if !error.is_catchable() {
+                    return Err(error);
+                }
+                return Ok(error.into_value());
+            }
+        } else {
+            self.push(value);
+        }
+
+        let (result, new_state) = loop {
+            if self.frames.is_empty() {
+                break (Ok(self.last_result.take().unwrap_or(Value::Nil)), FiberState::Done);
+            }
+            match self.step() {
+                Ok(()) => {
+                    if let Some(yielded) = self.pending_yield.take() {
+                        break (Ok(yielded), FiberState::Suspended);
+                    }
+                }
+                Err(error) if !error.is_catchable() => break (Err(error), FiberState::Done),
+                Err(error) => break (Ok(error.into_value()), FiberState::Done),
+            }
+        };
+
+        let (outer_frames, outer_stack) = self.suspended.pop().unwrap();
+        let fiber_frames = std::mem::replace(&mut self.frames, outer_frames);
+        let fiber_stack = std::mem::replace(&mut self.stack, outer_stack);
+
+        if let Some(fiber) = self.get_collectable_mut::<Fiber>(fiber_id) {
+            fiber.state = new_state;
+            if new_state == FiberState::Suspended {
+                fiber.frames = fiber_frames;
+                fiber.stack = fiber_stack;
+            }
+        }
+
+        result
+    }
+
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        if self.interrupt.load(Ordering::Relaxed) {
+            return Err(RuntimeError::Interrupted);
+        }
+        if let Some(remaining) = self.budget {
+            if remaining == 0 {
+                return Err(RuntimeError::BudgetExhausted);
+            }
+            self.budget = Some(remaining - 1);
+        }
+
         macro_rules! binary_op {
             ($op:tt, $type:tt) => {
                 let b = self.pop();
@@ -68,12 +536,49 @@ impl VM {
                 if let (Value::Number(a), Value::Number(b)) = (a.clone(), b.clone()) {
                     self.push(Value::$type(a $op b ));
                 } else {
-                    panic!("Invalid operands for binary operation.");
+                    return Err(RuntimeError::message("Invalid operands for binary operation."));
                 }
             };
         }
 
-        loop {
+        // Bitwise/shift operators have no meaningful `f64` semantics, so
+        // operands are truncated to `i64` for the operation and the result
+        // converted back to `Value::Number`.
+        macro_rules! int_binary_op {
+            ($op:tt) => {
+                let b = self.pop();
+                let a = self.pop();
+
+                if let (Value::Number(a), Value::Number(b)) = (a.clone(), b.clone()) {
+                    self.push(Value::Number(((a as i64) $op (b as i64)) as f64));
+                } else {
+                    return Err(RuntimeError::message("Invalid operands for binary operation."));
+                }
+            };
+        }
+
+        // `<<`/`>>` panic in debug builds (and are masked, not wrapped, in
+        // release) when the shift count is outside `0..64` -- unlike `&`/`|`/
+        // `^`, which are well-defined for any i64 pair -- so the shift count
+        // gets its own bounds check instead of going through `int_binary_op!`.
+        macro_rules! shift_op {
+            ($op:tt) => {
+                let b = self.pop();
+                let a = self.pop();
+
+                if let (Value::Number(a), Value::Number(b)) = (a.clone(), b.clone()) {
+                    let shift = b as i64;
+                    if !(0..64).contains(&shift) {
+                        return Err(RuntimeError::message("Shift amount must be between 0 and 63."));
+                    }
+                    self.push(Value::Number(((a as i64) $op shift) as f64));
+                } else {
+                    return Err(RuntimeError::message("Invalid operands for binary operation."));
+                }
+            };
+        }
+
+        {
             let instruction: Instruction = self.get_current_instruction();
             //dbg!(self.stack.clone());
             //dbg!(instruction.clone());
@@ -88,16 +593,16 @@ impl VM {
                 Instruction::True => self.stack.push(Value::Boolean(true)),
                 Instruction::False => self.stack.push(Value::Boolean(false)),
                 Instruction::Pop => { self.stack.pop(); },
-                Instruction::GetGlobal(index) => self.get_global(index),
+                Instruction::GetGlobal(index) => self.get_global(index)?,
                 Instruction::DefineGlobal(index) => self.define_global(index),
-                Instruction::SetGlobal(index) => self.set_global(index),
+                Instruction::SetGlobal(index) => self.set_global(index)?,
                 Instruction::GetLocal(index) => self.get_local(index),
                 Instruction::SetLocal(index) => self.set_local(index),
                 Instruction::GetUpvalue(index) => self.get_upvalue(index),
                 Instruction::SetUpvalue(index) => self.set_upvalue(index),
-                Instruction::GetProperty(index) => self.get_property(index),
-                Instruction::SetProperty(index) => self.set_property(index),
-                Instruction::GetSuper(index) => self.get_super(index),
+                Instruction::GetProperty(index) => self.get_property(index)?,
+                Instruction::SetProperty(index) => self.set_property(index)?,
+                Instruction::GetSuper(index) => self.get_super(index)?,
                 Instruction::Equal => {
                     let b = self.stack.pop().unwrap();
                     let a = self.stack.pop().unwrap();
@@ -105,16 +610,42 @@ impl VM {
                 }
                 Instruction::Greater => { binary_op!(>, Boolean); },
                 Instruction::Less => { binary_op!(<, Boolean); },
+                Instruction::GreaterEqual => { binary_op!(>=, Boolean); },
+                Instruction::LessEqual => { binary_op!(<=, Boolean); },
                 Instruction::Subtract => { binary_op!(-, Number); },
                 Instruction::Multiply => { binary_op!(*, Number); },
                 Instruction::Divide => { binary_op!(/, Number); },
+                Instruction::Modulo => { binary_op!(%, Number); },
+                Instruction::FloorDivide => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    if let (Value::Number(a), Value::Number(b)) = (a.clone(), b.clone()) {
+                        self.push(Value::Number((a / b).floor()));
+                    } else {
+                        return Err(RuntimeError::message("Invalid operands for binary operation."));
+                    }
+                }
+                Instruction::Power => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    if let (Value::Number(a), Value::Number(b)) = (a.clone(), b.clone()) {
+                        self.push(Value::Number(a.powf(b)));
+                    } else {
+                        return Err(RuntimeError::message("Invalid operands for binary operation."));
+                    }
+                }
+                Instruction::ShiftLeft => { shift_op!(<<); },
+                Instruction::ShiftRight => { shift_op!(>>); },
+                Instruction::BitAnd => { int_binary_op!(&); },
+                Instruction::BitOr => { int_binary_op!(|); },
+                Instruction::BitXor => { int_binary_op!(^); },
                 Instruction::Add => {
                     let b = self.stack.pop().unwrap();
                     let a = self.stack.pop().unwrap();
                     match (a, b) {
                         (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Number(a + b)),
                         (Value::String(a), Value::String(b)) => self.stack.push(Value::String(a + &b)),
-                        _ => panic!("Operands must be two numbers or two strings."),
+                        _ => return Err(RuntimeError::message("Operands must be two numbers or two strings.")),
                     }
                 }
                 Instruction::Not => {
@@ -126,7 +657,7 @@ impl VM {
                     if let Value::Number(value) = value {
                         self.stack.push(Value::Number(-value));
                     } else {
-                        panic!("Operand must be a number.");
+                        return Err(RuntimeError::message("Operand must be a number."));
                     }
                 }
                 Instruction::Print => {
@@ -145,23 +676,23 @@ impl VM {
                     self.frame_mut().ip -= offset;
                 }
                 Instruction::Call(arg_count) => {
-                    self.call_value_from_stack(arg_count);
+                    self.call_value_from_stack(arg_count)?;
                 }
                 Instruction::Invoke(index, arg_count) => {
                     let name = self.read_string(index);
-                    self.invoke(name, arg_count);
+                    self.invoke(name, arg_count)?;
                 }
                 Instruction::SuperInvoke(index, arg_count) => {
                     let name = self.read_string(index);
                     let superclass = self.stack.pop().unwrap();
                     match superclass {
                         Value::Class(class) => {
-                            self.invoke_from_class(class, name, arg_count);
+                            self.invoke_from_class(class, name, arg_count)?;
                         }
-                        _ => panic!("Only classes have superclass."),
+                        _ => return Err(RuntimeError::message("Only classes have superclass.")),
                     }
                 }
-                Instruction::Closure(index) => self.make_closure(index),
+                Instruction::Closure(index) => self.make_closure(index)?,
                 Instruction::CloseUpvalue => {
                     let index = self.stack.len().checked_sub(1).unwrap();
                     self.close_upvalues(index);
@@ -173,8 +704,9 @@ impl VM {
                     self.close_upvalues(base);
                     self.frames.pop();
                     if self.frames.is_empty() {
-                        self.stack.pop();
-                        return;
+                        self.stack.truncate(base);
+                        self.last_result = Some(result);
+                        return Ok(());
                     }
                     self.stack.truncate(base);
                     self.stack.push(result);
@@ -194,12 +726,29 @@ impl VM {
                             subclass.methods.insert(name.clone(), method.clone());
                         }
                     } else {
-                        panic!("Superclass must be a class.");
+                        return Err(RuntimeError::message("Superclass must be a class."));
                     }
                 }
-                Instruction::Method(index) => self.define_method(index),
+                Instruction::Method(index) => self.define_method(index)?,
+                Instruction::SetupTry(offset) => {
+                    let catch_ip = self.frame().ip + offset;
+                    let stack_len = self.stack.len();
+                    self.frame_mut().try_frames.push(TryFrame { catch_ip, stack_len });
+                }
+                Instruction::PopTry => {
+                    self.frame_mut().try_frames.pop();
+                }
+                Instruction::Throw => {
+                    let value = self.stack.pop().unwrap();
+                    return Err(RuntimeError::Thrown(value));
+                }
+                Instruction::Yield => {
+                    let value = self.stack.pop().unwrap();
+                    self.pending_yield = Some(value);
+                }
             }
         }
+        Ok(())
     }
 
     fn get_current_instruction(&self) -> Instruction {
@@ -233,12 +782,13 @@ impl VM {
         }
     }
 
-    fn get_global(&mut self, index: usize) {
+    fn get_global(&mut self, index: usize) -> Result<(), RuntimeError> {
         let name = self.read_string(index);
         if let Some(value) = self.globals.get(&name) {
             self.stack.push(value.clone());
+            Ok(())
         } else {
-            panic!("Undefined variable '{}'.", name);
+            Err(RuntimeError::message(format!("Undefined variable '{}'.", name)))
         }
     }
 
@@ -248,13 +798,14 @@ impl VM {
         self.globals.insert(name, value);
     }
 
-    fn set_global(&mut self, index: usize) {
+    fn set_global(&mut self, index: usize) -> Result<(), RuntimeError> {
         let name = self.read_string(index);
         if self.globals.contains_key(&name) {
             let value = self.peek(0).unwrap().clone();
             self.globals.insert(name, value);
+            Ok(())
         } else {
-            panic!("Undefined variable '{}'.", name);
+            Err(RuntimeError::message(format!("Undefined variable '{}'.", name)))
         }
     }
 
@@ -270,7 +821,7 @@ impl VM {
         self.stack[base + index] = value;
     }
 
-    fn make_closure(&mut self, index: usize) {
+    fn make_closure(&mut self, index: usize) -> Result<(), RuntimeError> {
         let constant = self.read_constant(index).clone();
         if let Value::Function(function) = constant {
             let mut upvalues = Vec::new();
@@ -285,8 +836,9 @@ impl VM {
 
             let closure = Value::Closure(function, upvalues);
             self.stack.push(closure);
+            Ok(())
         } else {
-            panic!("Value is not a function.");
+            Err(RuntimeError::message("Value is not a function."))
         }
     }
 
@@ -348,7 +900,7 @@ impl VM {
         }
     }
 
-    fn get_property(&mut self, index: usize) {
+    fn get_property(&mut self, index: usize) -> Result<(), RuntimeError> {
         let name = self.read_string(index);
         let instance = self.stack.pop().unwrap();
         match instance {
@@ -356,21 +908,42 @@ impl VM {
                 let instance = self.get_collectable::<Instance>(instance_ref).unwrap().clone();
                 if let Some(value) = instance.fields.get(&name) {
                     self.stack.push(value.clone());
+                    Ok(())
+                } else {
+                    self.bind_method(instance.class, instance_ref, name)
+                }
+            }
+            Value::Foreign(foreign_ref) => {
+                let class = self.get_collectable::<Foreign>(foreign_ref).unwrap().class;
+                let class = self.get_class(class).unwrap().clone();
+                if let Some(method) = class.methods.get(&name) {
+                    self.stack.push(method.clone());
+                    Ok(())
+                } else {
+                    Err(RuntimeError::message(format!("Undefined property '{}'.", name)))
+                }
+            }
+            Value::List(list_ref) => {
+                let class = self.get_collectable::<List>(list_ref).unwrap().class;
+                let class = self.get_class(class).unwrap().clone();
+                if let Some(method) = class.methods.get(&name) {
+                    self.stack.push(method.clone());
+                    Ok(())
                 } else {
-                    self.bind_method(instance.class, instance_ref, name);
+                    Err(RuntimeError::message(format!("Undefined property '{}'.", name)))
                 }
             }
-            _ => panic!("Only instances have properties."),
+            _ => Err(RuntimeError::message("Only instances have properties.")),
         }
     }
 
-    fn bind_method(&mut self, class: ClassRef, instance: InstanceRef, name: String) {
+    fn bind_method(&mut self, class: ClassRef, instance: InstanceRef, name: String) -> Result<(), RuntimeError> {
         let class = self.get_class(class).unwrap().clone();
         if let Some(method) = class.methods.get(&name) {
             let (function, upvalues) = match method {
                 Value::Function(f) => (f, Vec::new()),
                 Value::Closure(f, u) => (f, u.clone()),
-                _ => panic!("Expected function or closure."),
+                _ => return Err(RuntimeError::message("Expected function or closure.")),
             };
 
             self.stack.push(Value::BoundMethod {
@@ -378,12 +951,13 @@ impl VM {
                 function: function.clone(),
                 upvalues,
             });
+            Ok(())
         } else {
-            panic!("Undefined property '{}'.", name);
+            Err(RuntimeError::message(format!("Undefined property '{}'.", name)))
         }
     }
 
-    fn set_property(&mut self, index: usize) {
+    fn set_property(&mut self, index: usize) -> Result<(), RuntimeError> {
         let name = self.read_string(index);
         let value = self.pop().clone();
         let instance = self.pop().clone();
@@ -393,34 +967,42 @@ impl VM {
                 let mut instance = self.get_collectable_mut::<Instance>(instance_ref).unwrap();
                 instance.fields.insert(name, value.clone());
             }
-            _ => panic!("Only instances have fields."),
+            // Foreign objects expose state only through their registered
+            // methods (see `Invoke`), not as script-writable fields.
+            Value::Foreign(_) => return Err(RuntimeError::message("Foreign objects do not support field assignment.")),
+            // Same story for `List`: `items` is reached through `get`/`set`/
+            // etc., not through arbitrary field assignment.
+            Value::List(_) => return Err(RuntimeError::message("Lists do not support field assignment.")),
+            _ => return Err(RuntimeError::message("Only instances have fields.")),
         }
         self.push(value);
+        Ok(())
     }
 
-    fn get_super(&mut self, index: usize) {
+    fn get_super(&mut self, index: usize) -> Result<(), RuntimeError> {
         let (this_val, super_val) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
         if let (Value::Class(super_class), Value::Instance(this)) = (super_val, this_val) {
             let name = self.read_string(index);
-            self.bind_method(super_class, this, name);
+            self.bind_method(super_class, this, name)
         } else {
-            panic!("Superclass must be a class.")
+            Err(RuntimeError::message("Superclass must be a class."))
         }
     }
 
-    fn define_method(&mut self, index: usize) {
+    fn define_method(&mut self, index: usize) -> Result<(), RuntimeError> {
         let method = self.stack.pop().unwrap();
         let class = self.peek(0).unwrap().clone();
         if let Value::Class(class) = class {
             let name = self.read_string(index);
             let class = self.get_class_mut(class).unwrap();
             class.methods.insert(name, method);
+            Ok(())
         } else {
-            panic!("Expected class.");
+            Err(RuntimeError::message("Expected class."))
         }
     }
 
-    fn invoke(&mut self, method: String, arg_count: usize) {
+    fn invoke(&mut self, method: String, arg_count: usize) -> Result<(), RuntimeError> {
         let receiver = self.peek(arg_count).unwrap().clone();
         match receiver {
             Value::Instance(instance_ref) => {
@@ -428,53 +1010,106 @@ impl VM {
                 if let Some(method) = instance.fields.get(&method) {
                     let l = self.stack.len();
                     self.stack[l - arg_count - 1] = method.clone();
-                    self.call_value_from_stack(arg_count);
+                    self.call_value_from_stack(arg_count)
                 } else {
                     let class = instance.class;
-                    self.invoke_from_class(class, method, arg_count);
+                    self.invoke_from_class(class, method, arg_count)
                 }
             }
-            _ => panic!("Only instances have methods."),
+            Value::Foreign(foreign_ref) => {
+                let class = self.get_collectable::<Foreign>(foreign_ref).unwrap().class;
+                self.invoke_from_class(class, method, arg_count)
+            }
+            Value::List(list_ref) => {
+                let class = self.get_collectable::<List>(list_ref).unwrap().class;
+                self.invoke_from_class(class, method, arg_count)
+            }
+            _ => Err(RuntimeError::message("Only instances have methods.")),
         }
     }
 
-    fn invoke_from_class(&mut self, class: ClassRef, method: String, arg_count: usize) {
+    fn invoke_from_class(&mut self, class: ClassRef, method: String, arg_count: usize) -> Result<(), RuntimeError> {
         let class = self.get_class(class).unwrap().clone();
-        if let Some(method) = class.methods.get(&method) {
-            self.call_value(method.clone(), arg_count);
-        } else {
-            panic!("Undefined property '{}'.", method);
+        match class.methods.get(&method) {
+            // Script methods get the receiver bound as local 0 of the new
+            // frame via `call`/`call_value`'s other arms. Native methods
+            // have no frame to bind a receiver into, so `Map`/`List`'s own
+            // natives (and any native method like them) read it back out of
+            // `args` themselves -- which means it has to actually be in
+            // there: `self.peek(arg_count)` is the receiver here (unlike in
+            // `call_value`'s generic `NativeFunction` arm, where that same
+            // stack slot holds the callee for a plain, receiver-less call).
+            Some(Value::NativeFunction(function)) => {
+                let function = function.clone();
+                let from = self.stack.len() - arg_count - 1;
+                let args = self.stack[from..].to_vec();
+                let result = (function.function)(args, self);
+                self.pop_many(arg_count + 1);
+                if let Some(error) = self.pending_native_error.take() {
+                    return Err(error);
+                }
+                self.stack.push(result);
+                Ok(())
+            }
+            Some(method) => self.call_value(method.clone(), arg_count),
+            None => Err(RuntimeError::message(format!("Undefined property '{}'.", method))),
         }
     }
 
-    fn call_value(&mut self, callee: Value, arg_count: usize) {
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> Result<(), RuntimeError> {
         match callee {
             Value::Closure(function, upvalues) => {
-                self.call(function, upvalues, arg_count);
+                self.call(function, upvalues, arg_count)
             }
             Value::Function(function) => {
-                self.call(function, Vec::new(), arg_count);
+                self.call(function, Vec::new(), arg_count)
             }
-            Value::Class(class) => {
-                let instance = Instance::new(class.clone());
+            Value::Class(class_ref) => {
+                let class = self.get_class(class_ref).unwrap().clone();
+                if let Some(allocator) = class.foreign.clone() {
+                    // Foreign classes skip the script `Instance`/`init`
+                    // path entirely: the allocator alone builds the opaque
+                    // Rust state from the constructor arguments.
+                    let from = self.stack.len() - arg_count;
+                    let args = self.stack[from..].to_vec();
+                    let data = allocator(args, self);
+                    let id = self.new_collectable(Foreign { class: class_ref, data });
+                    self.pop_many(arg_count + 1);
+                    self.stack.push(Value::Foreign(id));
+                    return Ok(());
+                }
+
+                if class.is_list {
+                    // Same idea as the `foreign` path above, but for the one
+                    // builtin that needs a real `Vec<Value>` rather than
+                    // opaque host state: `List()` allocates an empty `List`
+                    // collectable instead of an `Instance`.
+                    let id = self.new_collectable(List::new(class_ref, Vec::new()));
+                    self.pop_many(arg_count + 1);
+                    self.stack.push(Value::List(id));
+                    return Ok(());
+                }
+
+                let instance = Instance::new(class_ref);
                 let instance_ref = self.new_collectable(instance);
                 let value = Value::Instance(instance_ref);
                 let l = self.stack.len();
                 self.stack[l - arg_count - 1] = value;
 
-                let class = self.get_class(class).unwrap().clone();
                 if let Some(init) = class.methods.get("init") {
                     match init {
                         Value::Closure(function, upvalues) => {
-                            self.call(function.clone(), upvalues.clone(), arg_count);
+                            self.call(function.clone(), upvalues.clone(), arg_count)
                         }
                         Value::Function(function) => {
-                            self.call(function.clone(), Vec::new(), arg_count);
+                            self.call(function.clone(), Vec::new(), arg_count)
                         }
-                        _ => panic!("Expected function."),
+                        _ => Err(RuntimeError::message("Expected function.")),
                     }
                 } else if arg_count != 0 {
-                    panic!("Expected 0 arguments but got {}.", arg_count);
+                    Err(RuntimeError::message(format!("Expected 0 arguments but got {}.", arg_count)))
+                } else {
+                    Ok(())
                 }
             }
             Value::BoundMethod {
@@ -484,37 +1119,51 @@ impl VM {
             } => {
                 let l = self.stack.len();
                 self.stack[l - arg_count - 1] = Value::Instance(receiver);
-                self.call(function, upvalues, arg_count);
+                self.call(function, upvalues, arg_count)
             }
             Value::NativeFunction(function) => {
                 let from = self.stack.len() - arg_count;
                 let args = self.stack[from..].to_vec();
                 let result = (function.function)(args, self);
                 self.pop_many(arg_count + 1);
+                if let Some(error) = self.pending_native_error.take() {
+                    return Err(error);
+                }
                 self.stack.push(result);
+                Ok(())
             }
-            _ => panic!("Can only call functions and classes."),
+            Value::Fiber(fiber_id) => {
+                let from = self.stack.len() - arg_count;
+                let resume_value = self.stack[from..].first().cloned().unwrap_or(Value::Nil);
+                let result = self.resume_fiber(fiber_id, resume_value);
+                self.pop_many(arg_count + 1);
+                self.stack.push(result?);
+                Ok(())
+            }
+            _ => Err(RuntimeError::message("Can only call functions and classes.")),
         }
     }
 
-    fn call_value_from_stack(&mut self, arg_count: usize) {
+    fn call_value_from_stack(&mut self, arg_count: usize) -> Result<(), RuntimeError> {
         let callee = self.peek(arg_count).unwrap().clone();
-        self.call_value(callee, arg_count);
+        self.call_value(callee, arg_count)
     }
 
-    fn call(&mut self, function: Function, upvalues: Vec<UpvalueRegistryRef>, arg_count: usize) {
+    fn call(&mut self, function: Function, upvalues: Vec<UpvalueRegistryRef>, arg_count: usize) -> Result<(), RuntimeError> {
         if arg_count != function.arity {
-            panic!(
+            return Err(RuntimeError::message(format!(
                 "Expected {} arguments but got {}.",
                 function.arity, arg_count
-            );
+            )));
         }
         self.frames.push(CallFrame {
             function,
             ip: 0,
             base: self.stack.len() - arg_count - 1,
             upvalues,
+            try_frames: Vec::new(),
         });
+        Ok(())
     }
 
     fn pop_many(&mut self, count: usize) {
@@ -532,6 +1181,7 @@ impl VM {
     }
 
     pub fn new_instance(&mut self, instance: Instance) -> Value {
+        self.maybe_collect_garbage();
         let id = self.heap.next_id;
         self.heap.next_id += 1;
         self.heap.objects.insert(id, Box::new(instance));
@@ -553,12 +1203,37 @@ impl VM {
     }
 
     pub(crate) fn new_class(&mut self, class: Class) -> Value {
+        self.maybe_collect_garbage();
         let id = self.heap.next_id;
         self.heap.next_id += 1;
         self.heap.objects.insert(id, Box::new(class));
         Value::Class(id)
     }
 
+    /// Registers a host-backed (foreign) class under `name`: calling it
+    /// from a script runs `allocator` on the constructor arguments instead
+    /// of building a script `Instance`, and `methods` become its
+    /// `Invoke`-dispatched native methods (just like `Map`/`List`'s own
+    /// native methods, which this reuses the class-method machinery for).
+    /// Returns the class value, already bound as a global under `name`.
+    pub fn define_foreign_class(
+        &mut self,
+        name: impl Into<String>,
+        allocator: ForeignAllocator,
+        methods: HashMap<String, NativeFunction>,
+    ) -> Value {
+        let name = name.into();
+        let class = Class {
+            name: name.clone(),
+            methods: methods.into_iter().map(|(method_name, f)| (method_name, Value::NativeFunction(f))).collect(),
+            foreign: Some(allocator),
+            is_list: false,
+        };
+        let value = self.new_class(class);
+        self.globals.insert(name, value.clone());
+        value
+    }
+
     pub(crate) fn get_class(&self, id: usize) -> Option<&Class> {
         match self.heap.objects.get(&id) {
             Some(collectable) => collectable.as_any().downcast_ref::<Class>(),
@@ -588,12 +1263,67 @@ impl VM {
     }
 
     pub fn new_collectable<T: Collectable>(&mut self, collectable: T) -> usize {
+        self.maybe_collect_garbage();
         let id = self.heap.next_id;
         self.heap.next_id += 1;
         self.heap.objects.insert(id, Box::new(collectable));
         id
     }
 
+    fn maybe_collect_garbage(&mut self) {
+        if self.heap.objects.len() >= self.heap.next_gc {
+            self.collect_garbage();
+        }
+    }
+
+    /// Runs a full mark-and-sweep pass over the heap: marks every id
+    /// reachable from the roots (the value stack, globals, each frame's
+    /// captured upvalues, the open-upvalue list, any outer frames/stack
+    /// parked in `self.suspended` while a fiber is running, and anything
+    /// pinned via `pin_root`), then drops
+    /// anything left unmarked. Called automatically by the allocating
+    /// methods once the heap has grown past `next_gc`; also callable
+    /// directly for tests or an embedder that wants to force a collection.
+    pub fn collect_garbage(&mut self) {
+        let mut worklist: Vec<usize> = Vec::new();
+        for value in &self.stack {
+            trace_value(value, &mut worklist);
+        }
+        for value in self.globals.values() {
+            trace_value(value, &mut worklist);
+        }
+        for upvalue in &self.open_upvalues {
+            worklist.push(*upvalue);
+        }
+        for frame in &self.frames {
+            worklist.extend(frame.upvalues.iter().copied());
+        }
+        for (frames, stack) in &self.suspended {
+            for value in stack {
+                trace_value(value, &mut worklist);
+            }
+            for frame in frames {
+                worklist.extend(frame.upvalues.iter().copied());
+            }
+        }
+        for value in &self.native_roots {
+            trace_value(value, &mut worklist);
+        }
+
+        let mut reachable: HashSet<usize> = HashSet::new();
+        while let Some(id) = worklist.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(object) = self.heap.objects.get(&id) {
+                object.trace(&mut worklist);
+            }
+        }
+
+        self.heap.objects.retain(|id, _| reachable.contains(id));
+        self.heap.next_gc = (self.heap.objects.len() * HEAP_GROW_FACTOR).max(MIN_HEAP_THRESHOLD);
+    }
+
 }
 
 #[derive(Clone)]
@@ -610,6 +1340,12 @@ impl Collectable for UpvalueRegistry {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn trace(&self, worklist: &mut Vec<usize>) {
+        if let UpvalueRegistry::Closed(value) = self {
+            trace_value(value, worklist);
+        }
+    }
 }
 
 impl UpvalueRegistry {
@@ -641,4 +1377,51 @@ impl PartialEq for UpvalueRegistry {
 pub struct FunctionUpvalue {
     pub index: usize,
     pub is_local: bool,
+}
+
+/// A fiber's run state. `create(fn)` starts a fiber `Suspended` with no
+/// saved frames yet; `resume` transitions it to `Running` for the duration
+/// of the call, then back to `Suspended` (with its frames/stack saved) on
+/// `yield`, or to `Done` once its function returns or raises.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FiberState {
+    Suspended,
+    Running,
+    Done,
+}
+
+/// A suspendable call stack: the `Fiber` heap object backing `create`/
+/// `resume`. While suspended it owns a private slice of frames and stack
+/// values, completely disjoint from whichever VM state resumed it; `resume`
+/// swaps them into `self.frames`/`self.stack` for the duration of the call
+/// and swaps the (possibly updated) versions back out afterward.
+pub struct Fiber {
+    state: FiberState,
+    /// The closure to start on the fiber's first resume; taken (and left
+    /// `None`) once that first resume happens.
+    closure: Option<Value>,
+    frames: Vec<CallFrame>,
+    stack: Vec<Value>,
+}
+
+impl Collectable for Fiber {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn trace(&self, worklist: &mut Vec<usize>) {
+        if let Some(closure) = &self.closure {
+            trace_value(closure, worklist);
+        }
+        for value in &self.stack {
+            trace_value(value, worklist);
+        }
+        for frame in &self.frames {
+            worklist.extend(frame.upvalues.iter().copied());
+        }
+    }
 }
\ No newline at end of file